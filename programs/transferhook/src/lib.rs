@@ -6,14 +6,31 @@ use {
                     transfer_hook::TransferHookAccount,
                     BaseStateWithExtensions, StateWithExtensions,
                 },
-                state::Account as Token2022Account,
+                state::{Account as Token2022Account, Mint as Token2022Mint},
             },
+    solana_program::program_option::COption,
     spl_transfer_hook_interface::error::TransferHookError,
 };
-use spl_transfer_hook_interface::instruction::TransferHookInstruction;
+use spl_tlv_account_resolution::seeds::Seed;
+use spl_transfer_hook_interface::get_extra_account_metas_address;
+
+pub mod offchain;
 
 declare_id!("7aeu4HRHR4UwQndRDyh5f7nMwgxgH3rrtLgRntxdivZw");
 
+/// Seed config for a per-transfer "delegate" PDA: `[b"delegate", source, amount_le_bytes]`.
+/// Account key `index` 0 is `source` and instruction data bytes 8..16 are the `u64` amount in the
+/// `Execute` instruction (the first 8 bytes are the instruction discriminator). Exposed so
+/// callers can turn it into an `ExtraAccountMeta` (via `ExtraAccountMeta::new_with_seeds`) and
+/// pass it into `initialize_extra_account_meta_list`'s `additional_account_metas`.
+pub fn delegate_seeds() -> Vec<Seed> {
+    vec![
+        Seed::Literal { bytes: b"delegate".to_vec() },
+        Seed::AccountKey { index: 0 },
+        Seed::InstructionData { index: 8, length: 8 },
+    ]
+}
+
 fn check_token_account_is_transferring(account_data: &[u8]) -> Result<()> {
 	let token_account = StateWithExtensions::<Token2022Account>::unpack(account_data)?;
 	let extension = token_account.get_extension::<TransferHookAccount>()?;
@@ -28,16 +45,26 @@ fn check_token_account_is_transferring(account_data: &[u8]) -> Result<()> {
 
 #[program]
 pub mod transferhook {
-    use solana_program::program::invoke_signed;
+    use solana_program::program::{invoke, invoke_signed};
     use solana_program::system_instruction;
     use spl_transfer_hook_interface::collect_extra_account_metas_signer_seeds;
     use spl_transfer_hook_interface::instruction::ExecuteInstruction;
     use spl_tlv_account_resolution::state::ExtraAccountMetaList;
     use spl_tlv_account_resolution::account::ExtraAccountMeta;
+    use spl_tlv_account_resolution::seeds::Seed;
     use spl_pod::primitives::PodBool;
 
     use super::*;
 
+    /// Seed config for the per-mint `Policy` PDA: `[b"policy", mint]`. Account key `index` 1 is
+    /// `mint` in the `Execute` account list.
+    fn policy_seeds() -> Vec<Seed> {
+        vec![
+            Seed::Literal { bytes: b"policy".to_vec() },
+            Seed::AccountKey { index: 1 },
+        ]
+    }
+
     /// Initialize the counter account.
     /// This function creates a new counter account and sets the owner to the authority.
     /// The counter account is used to count the number of times the transfer hook has been invoked.
@@ -48,17 +75,56 @@ pub mod transferhook {
         Ok(())
     }
 
+    #[interface(spl_transfer_hook_interface::instruction::ExecuteInstruction)]
     pub fn transfer_hook<'a>(ctx: Context<TransferHook>, amount: u64) -> Result<()> {
-        // Count the number of times the transfer hook has been invoked.
-        let counter = &mut ctx.accounts.counter;
-        counter.count += 1;
-
         let source_account = &ctx.accounts.source;
-    	let destination_account = &ctx.accounts.destination;
+        let mint_account = &ctx.accounts.mint;
+        let destination_account = &ctx.accounts.destination;
+        let authority_account = &ctx.accounts.authority;
+
+        // Inside the Execute CPI, Token-2022 de-escalates source/mint/destination/authority to
+        // read-only non-signers. A caller invoking this program directly (not via Token-2022)
+        // could instead pass writable/signer versions to trick the logic below, so reject that.
+        for account in [source_account, mint_account, destination_account, authority_account] {
+            if account.is_signer || account.is_writable {
+                return Err(Into::<ProgramError>::into(TransferHookError::IncorrectAccount))?;
+            }
+        }
+
+        // The mint recorded on both the source and destination token accounts must match the
+        // mint account that was passed in, and neither owner may be on the policy's blocklist.
+        {
+            let source_data = source_account.try_borrow_data()?;
+            let source_state = StateWithExtensions::<Token2022Account>::unpack(&source_data)?;
+            let destination_data = destination_account.try_borrow_data()?;
+            let destination_state = StateWithExtensions::<Token2022Account>::unpack(&destination_data)?;
+            if source_state.base.mint != mint_account.key() || destination_state.base.mint != mint_account.key() {
+                return Err(Into::<ProgramError>::into(TransferHookError::IncorrectAccount))?;
+            }
+
+            let policy = &ctx.accounts.policy;
+            require!(!policy.frozen, TransferhookError::MintFrozen);
+            require!(amount <= policy.max_amount, TransferhookError::AmountExceedsCeiling);
+            require!(
+                !policy.blocked_owners.contains(&source_state.base.owner)
+                    && !policy.blocked_owners.contains(&destination_state.base.owner),
+                TransferhookError::OwnerBlocked
+            );
+        }
+
+        // The extra account PDA must be the one Token-2022 derives for this mint and program.
+        let expected_extra_account = get_extra_account_metas_address(&mint_account.key(), ctx.program_id);
+        if ctx.accounts.extra_account.key() != expected_extra_account {
+            return Err(Into::<ProgramError>::into(TransferHookError::IncorrectAccount))?;
+        }
 
         check_token_account_is_transferring(&source_account.to_account_info().try_borrow_data()?)?;
     	check_token_account_is_transferring(&destination_account.to_account_info().try_borrow_data()?)?;
 
+        // Count the number of times the transfer hook has been invoked.
+        let counter = &mut ctx.accounts.counter;
+        counter.count += 1;
+
         msg!("Transfer hook invoked");
         msg!("Transfer amount: {}", amount);
         msg!("Transfer with extra account PDA: {}", ctx.accounts.extra_account.key());
@@ -69,15 +135,29 @@ pub mod transferhook {
     /// Initialize the extra account meta list.
     /// This function creates a new extra account meta list and allocates the extra account PDA.
     /// The extra account PDA is used to store the extra account meta list.
-    pub fn initialize_extra_account_meta_list(ctx: Context<InitializeExtraAccountMetaList>, bump_seed: u8) -> Result<()> {
-        // Create the extra account meta list.
-        let account_metas = vec![
+    ///
+    /// `additional_account_metas` are appended after the two metas this program always wires in
+    /// (the counter and the policy), letting callers declare further seed-resolved accounts
+    /// (literal/PDA/external-PDA `ExtraAccountMeta`s built from `Seed::Literal`,
+    /// `Seed::InstructionData`, `Seed::AccountKey`, and `Seed::AccountData` descriptors) without
+    /// this program hard-coding what they are.
+    pub fn initialize_extra_account_meta_list(
+        ctx: Context<InitializeExtraAccountMetaList>,
+        bump_seed: u8,
+        additional_account_metas: Vec<ExtraAccountMeta>,
+    ) -> Result<()> {
+        // `policy` must stay right after `counter` since `TransferHook` reads it by position;
+        // whatever the caller supplies is appended after both.
+        let mut account_metas = vec![
             ExtraAccountMeta {
                 discriminator: 0,
                 address_config: ctx.accounts.counter.key().to_bytes(),
                 is_signer: PodBool::from(false),
                 is_writable: PodBool::from(true),
-            }];
+            },
+            ExtraAccountMeta::new_with_seeds(&policy_seeds(), false, false)?,
+        ];
+        account_metas.extend(additional_account_metas);
 
         // Allocate extra account PDA account.
         let bump_seed = [bump_seed];
@@ -102,16 +182,89 @@ pub mod transferhook {
         Ok(())
     }
 
-    /// Fallback function to handle the transfer hook instruction.
-    pub fn fallback<'a>(program_id: &Pubkey, accounts: &'a[AccountInfo<'a>], data: &[u8]) -> Result<()> {
-        let instruction = TransferHookInstruction::unpack(data)?;
-        match instruction {
-            TransferHookInstruction::Execute { amount } => {
-                let amount = amount.to_le_bytes();
-                __private::__global::transfer_hook(program_id, accounts, &amount)
-            }
-            _ => Err(ProgramError::InvalidInstructionData.into()),
+    /// Update the extra account meta list.
+    /// This function reallocates the extra account PDA to fit the new list and rewrites it,
+    /// topping up lamports from the authority to stay rent-exempt at the new size; when the
+    /// account shrinks, its surplus lamports are not refunded.
+    /// Only the mint's policy authority may call this.
+    pub fn update_extra_account_meta_list(
+        ctx: Context<UpdateExtraAccountMetaList>,
+        extra_account_metas: Vec<ExtraAccountMeta>,
+    ) -> Result<()> {
+        let extra_account_info = ctx.accounts.extra_account.to_account_info();
+        let account_size = ExtraAccountMetaList::size_of(extra_account_metas.len())?;
+
+        // Top up lamports to stay rent-exempt at the new size before reallocating.
+        let new_minimum_balance = Rent::get()?.minimum_balance(account_size);
+        let lamports_diff = new_minimum_balance.saturating_sub(extra_account_info.lamports());
+        if lamports_diff > 0 {
+            invoke(
+                &system_instruction::transfer(ctx.accounts.authority.key, extra_account_info.key, lamports_diff),
+                &[
+                    ctx.accounts.authority.to_account_info(),
+                    extra_account_info.clone(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+        extra_account_info.realloc(account_size, false)?;
+
+        // Rewrite the extra account meta list in place.
+        let mut data = extra_account_info.try_borrow_mut_data()?;
+        ExtraAccountMetaList::update::<ExecuteInstruction>(&mut data, &extra_account_metas)?;
+
+        msg!("Extra account meta list updated on {}", extra_account_info.key());
+        Ok(())
+    }
+
+    /// Initialize the transfer policy for a mint: an unblocked, unfrozen policy with the given
+    /// per-transfer amount ceiling. Only the mint's own mint authority may do this, so the
+    /// legitimate authority can't be front-run into a policy someone else controls.
+    pub fn initialize_policy(ctx: Context<InitializePolicy>, max_amount: u64) -> Result<()> {
+        {
+            let mint_data = ctx.accounts.mint.try_borrow_data()?;
+            let mint_state = StateWithExtensions::<Token2022Mint>::unpack(&mint_data)?;
+            require!(
+                mint_state.base.mint_authority == COption::Some(ctx.accounts.authority.key()),
+                TransferhookError::IncorrectMintAuthority
+            );
         }
+
+        let policy = &mut ctx.accounts.policy;
+        policy.authority = ctx.accounts.authority.key();
+        policy.mint = ctx.accounts.mint.key();
+        policy.max_amount = max_amount;
+        policy.frozen = false;
+        policy.blocked_owners = Vec::new();
+        Ok(())
+    }
+
+    /// Update the transfer policy: ceiling, frozen flag, and/or blocklist. Only the policy's
+    /// authority may call this. Omitted fields are left unchanged.
+    pub fn set_policy(
+        ctx: Context<SetPolicy>,
+        max_amount: Option<u64>,
+        frozen: Option<bool>,
+        blocked_owners: Option<Vec<Pubkey>>,
+    ) -> Result<()> {
+        if let Some(blocked_owners) = &blocked_owners {
+            require!(
+                blocked_owners.len() <= Policy::MAX_BLOCKED_OWNERS,
+                TransferhookError::TooManyBlockedOwners
+            );
+        }
+
+        let policy = &mut ctx.accounts.policy;
+        if let Some(max_amount) = max_amount {
+            policy.max_amount = max_amount;
+        }
+        if let Some(frozen) = frozen {
+            policy.frozen = frozen;
+        }
+        if let Some(blocked_owners) = blocked_owners {
+            policy.blocked_owners = blocked_owners;
+        }
+        Ok(())
     }
 }
 
@@ -148,6 +301,11 @@ pub struct TransferHook<'info> {
     pub extra_account: AccountInfo<'info>,
     /// CHECK:
     pub counter: Account<'info, Counter>,
+    #[account(
+        seeds = [b"policy", mint.key().as_ref()],
+        bump)
+    ]
+    pub policy: Account<'info, Policy>,
 }
 
 #[derive(Accounts)]
@@ -167,8 +325,88 @@ pub struct InitializeExtraAccountMetaList<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct UpdateExtraAccountMetaList<'info> {
+    /// CHECK: must be the extra account PDA
+    #[account(mut,
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump)
+    ]
+    pub extra_account: AccountInfo<'info>,
+    #[account(
+        seeds = [b"policy", mint.key().as_ref()],
+        bump,
+        has_one = authority @ TransferhookError::Unauthorized)
+    ]
+    pub policy: Account<'info, Policy>,
+    /// CHECK:
+    pub mint: AccountInfo<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePolicy<'info> {
+    #[account(init,
+        seeds = [b"policy", mint.key().as_ref()],
+        bump,
+        payer = authority,
+        space = Policy::SIZE)
+    ]
+    pub policy: Account<'info, Policy>,
+    /// CHECK:
+    pub mint: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPolicy<'info> {
+    #[account(mut,
+        seeds = [b"policy", mint.key().as_ref()],
+        bump,
+        has_one = authority @ TransferhookError::Unauthorized)
+    ]
+    pub policy: Account<'info, Policy>,
+    /// CHECK:
+    pub mint: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+}
+
 #[account]
 pub struct Counter {
     pub owner: Pubkey,
     pub count: u64,
 }
+
+#[account]
+pub struct Policy {
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub max_amount: u64,
+    pub frozen: bool,
+    pub blocked_owners: Vec<Pubkey>,
+}
+
+impl Policy {
+    pub const MAX_BLOCKED_OWNERS: usize = 16;
+    pub const SIZE: usize = 8 + 32 + 32 + 8 + 1 + 4 + 32 * Self::MAX_BLOCKED_OWNERS;
+}
+
+#[error_code]
+pub enum TransferhookError {
+    #[msg("Signer is not the authority recorded on this account")]
+    Unauthorized,
+    #[msg("The mint is frozen for transfers by its policy")]
+    MintFrozen,
+    #[msg("Transfer amount exceeds the policy's per-transfer ceiling")]
+    AmountExceedsCeiling,
+    #[msg("Source or destination owner is blocked by the mint's policy")]
+    OwnerBlocked,
+    #[msg("Too many blocked owners for the policy account's allocated space")]
+    TooManyBlockedOwners,
+    #[msg("Signer is not the mint's recorded mint authority")]
+    IncorrectMintAuthority,
+}
@@ -0,0 +1,86 @@
+#![cfg(feature = "client")]
+
+//! Off-chain helpers for resolving the extra accounts this program's `transfer_hook`
+//! instruction needs at transfer time.
+//!
+//! Resolving against a bare `TransferChecked` instruction is a well-known footgun: some of
+//! this program's `ExtraAccountMeta`s (e.g. the delegate seed config in [`crate::delegate_seeds`])
+//! read `Seed::InstructionData` bytes out of what must be the interface's `Execute`-shaped data
+//! (an 8-byte discriminator followed by the `u64` amount), not `TransferChecked`'s own wire
+//! format. So this helper builds a synthetic `Execute` instruction to resolve against, then
+//! copies only the newly-resolved accounts onto the caller's real instruction.
+
+use {
+    solana_program::{
+        instruction::{AccountMeta, Instruction},
+        program_error::ProgramError,
+        pubkey::Pubkey,
+    },
+    spl_tlv_account_resolution::state::ExtraAccountMetaList,
+    spl_transfer_hook_interface::{
+        get_extra_account_metas_address,
+        instruction::{execute, ExecuteInstruction},
+    },
+    std::future::Future,
+};
+
+/// Resolve and append every extra account this program's transfer hook needs to a
+/// `TransferChecked` instruction, in the order the on-chain `Execute` dispatch expects.
+///
+/// `fetch_account_data_fn` is called with each account's pubkey (starting with the validation
+/// PDA) and must return its current account data, e.g. via an RPC `getAccountInfo` call.
+pub async fn add_extra_account_metas_for_execute<F, Fut>(
+    transfer_checked_instruction: &mut Instruction,
+    program_id: &Pubkey,
+    source_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    amount: u64,
+    mut fetch_account_data_fn: F,
+) -> Result<(), ProgramError>
+where
+    F: FnMut(Pubkey) -> Fut,
+    Fut: Future<Output = Result<Option<Vec<u8>>, ProgramError>>,
+{
+    let validate_state_pubkey = get_extra_account_metas_address(mint_pubkey, program_id);
+    let validate_state_data = fetch_account_data_fn(validate_state_pubkey)
+        .await?
+        .ok_or(ProgramError::UninitializedAccount)?;
+
+    // Resolve against a synthetic instruction shaped exactly like the interface's `Execute`
+    // (8-byte discriminator + `u64` amount, with source/mint/destination/authority/validate_state
+    // as the first five accounts) so seed configs that read instruction-data bytes resolve the
+    // same PDAs Token-2022 derives on-chain.
+    let mut execute_instruction = execute(
+        program_id,
+        source_pubkey,
+        mint_pubkey,
+        destination_pubkey,
+        authority_pubkey,
+        &validate_state_pubkey,
+        amount,
+    );
+
+    ExtraAccountMetaList::add_to_instruction::<ExecuteInstruction, _, _>(
+        &mut execute_instruction,
+        &mut fetch_account_data_fn,
+        &validate_state_data,
+    )
+    .await?;
+
+    // The first five accounts of `execute_instruction` are the base Execute accounts; everything
+    // after that is what got resolved from the validation account's extra metas. Token-2022
+    // also needs the hook program and the validation account among the transfer's accounts.
+    transfer_checked_instruction
+        .accounts
+        .extend(execute_instruction.accounts.drain(5..));
+    transfer_checked_instruction
+        .accounts
+        .push(AccountMeta::new_readonly(*program_id, false));
+    transfer_checked_instruction
+        .accounts
+        .push(AccountMeta::new_readonly(validate_state_pubkey, false));
+
+    Ok(())
+}
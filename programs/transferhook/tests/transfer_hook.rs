@@ -0,0 +1,252 @@
+use {
+    anchor_lang::{InstructionData, ToAccountMetas},
+    solana_program_test::{processor, tokio, ProgramTest},
+    solana_sdk::{
+        instruction::Instruction,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_instruction,
+        transaction::Transaction,
+    },
+    spl_tlv_account_resolution::{account::ExtraAccountMeta, state::ExtraAccountMetaList},
+    spl_token_2022::{
+        extension::{transfer_hook::instruction::initialize as initialize_transfer_hook, ExtensionType},
+        instruction as token_instruction,
+    },
+    spl_transfer_hook_interface::get_extra_account_metas_address,
+    transferhook::{accounts, delegate_seeds, instruction, offchain},
+};
+
+/// End-to-end check that a mint configured with this program as its transfer hook can complete a
+/// `TransferChecked` once the client resolves the seed-derived extra accounts via
+/// [`offchain::add_extra_account_metas_for_execute`] rather than a bare `TransferChecked`.
+#[tokio::test]
+async fn transfer_succeeds_with_seed_derived_accounts() {
+    let program_test = ProgramTest::new(
+        "transferhook",
+        transferhook::id(),
+        processor!(transferhook::entry),
+    );
+
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+
+    let mint = Keypair::new();
+    let mint_authority = Keypair::new();
+    let decimals = 0;
+
+    let mint_len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[
+        ExtensionType::TransferHook,
+    ])
+    .unwrap();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let mint_rent = rent.minimum_balance(mint_len);
+
+    let init_mint_tx = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &mint.pubkey(),
+                mint_rent,
+                mint_len as u64,
+                &spl_token_2022::id(),
+            ),
+            initialize_transfer_hook(
+                &spl_token_2022::id(),
+                &mint.pubkey(),
+                Some(mint_authority.pubkey()),
+                Some(transferhook::id()),
+            )
+            .unwrap(),
+            token_instruction::initialize_mint2(
+                &spl_token_2022::id(),
+                &mint.pubkey(),
+                &mint_authority.pubkey(),
+                None,
+                decimals,
+            )
+            .unwrap(),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &mint],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(init_mint_tx).await.unwrap();
+
+    // Initialize the extra account meta list (counter + policy + delegate seed configs).
+    let (counter, _) = Pubkey::find_program_address(&[payer.pubkey().as_ref()], &transferhook::id());
+    let (extra_account_meta_list, bump) = Pubkey::find_program_address(
+        &[b"extra-account-metas", mint.pubkey().as_ref()],
+        &transferhook::id(),
+    );
+    let (policy, _) = Pubkey::find_program_address(&[b"policy", mint.pubkey().as_ref()], &transferhook::id());
+
+    let extra_account_metas = vec![ExtraAccountMeta::new_with_seeds(&delegate_seeds(), false, true).unwrap()];
+    // `initialize_extra_account_meta_list` allocates/assigns/writes into the PDA directly via
+    // the system program rather than `#[account(init, ...)]`, so nothing funds it for rent
+    // exemption on its own; fund it up front or the runtime rejects the account's data as soon
+    // as it holds non-zero data at zero lamports.
+    let extra_account_size = ExtraAccountMetaList::size_of(1 + 1 + extra_account_metas.len()).unwrap();
+    let extra_account_rent = rent.minimum_balance(extra_account_size);
+
+    let setup_tx = Transaction::new_signed_with_payer(
+        &[
+            Instruction {
+                program_id: transferhook::id(),
+                accounts: accounts::Initialize {
+                    counter,
+                    system_program: solana_sdk::system_program::id(),
+                    authority: payer.pubkey(),
+                }
+                .to_account_metas(None),
+                data: instruction::Initialize {}.data(),
+            },
+            // `initialize_policy` is paid for by its authority, here the otherwise unfunded mint authority.
+            system_instruction::transfer(
+                &payer.pubkey(),
+                &mint_authority.pubkey(),
+                rent.minimum_balance(transferhook::Policy::SIZE),
+            ),
+            Instruction {
+                program_id: transferhook::id(),
+                accounts: accounts::InitializePolicy {
+                    policy,
+                    mint: mint.pubkey(),
+                    system_program: solana_sdk::system_program::id(),
+                    authority: mint_authority.pubkey(),
+                }
+                .to_account_metas(None),
+                data: instruction::InitializePolicy { max_amount: u64::MAX }.data(),
+            },
+            system_instruction::transfer(&payer.pubkey(), &extra_account_meta_list, extra_account_rent),
+            Instruction {
+                program_id: transferhook::id(),
+                accounts: accounts::InitializeExtraAccountMetaList {
+                    extra_account: extra_account_meta_list,
+                    counter,
+                    mint: mint.pubkey(),
+                    authority: payer.pubkey(),
+                    system_program: solana_sdk::system_program::id(),
+                }
+                .to_account_metas(None),
+                data: instruction::InitializeExtraAccountMetaList {
+                    bump_seed: bump,
+                    additional_account_metas: extra_account_metas,
+                }
+                .data(),
+            },
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &mint_authority],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(setup_tx).await.unwrap();
+
+    // Fund source and destination token accounts.
+    let source_owner = Keypair::new();
+    let destination_owner = Keypair::new();
+    let source = create_and_fund_token_account(&mut context, &mint.pubkey(), &source_owner.pubkey(), &mint_authority, 100).await;
+    let destination = create_token_account(&mut context, &mint.pubkey(), &destination_owner.pubkey()).await;
+
+    let amount = 10u64;
+    let mut transfer_ix = token_instruction::transfer_checked(
+        &spl_token_2022::id(),
+        &source,
+        &mint.pubkey(),
+        &destination,
+        &source_owner.pubkey(),
+        &[],
+        amount,
+        decimals,
+    )
+    .unwrap();
+
+    assert_eq!(
+        get_extra_account_metas_address(&mint.pubkey(), &transferhook::id()),
+        extra_account_meta_list
+    );
+    offchain::add_extra_account_metas_for_execute(
+        &mut transfer_ix,
+        &transferhook::id(),
+        &source,
+        &mint.pubkey(),
+        &destination,
+        &source_owner.pubkey(),
+        amount,
+        |pubkey| {
+            let mut banks_client = context.banks_client.clone();
+            async move { Ok(banks_client.get_account(pubkey).await.unwrap().map(|account| account.data)) }
+        },
+    )
+    .await
+    .unwrap();
+
+    let transfer_tx = Transaction::new_signed_with_payer(
+        &[transfer_ix],
+        Some(&payer.pubkey()),
+        &[&payer, &source_owner],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(transfer_tx).await.unwrap();
+
+    let counter_account = context.banks_client.get_account(counter).await.unwrap().unwrap();
+    let counter_state: transferhook::Counter =
+        anchor_lang::AccountDeserialize::try_deserialize(&mut counter_account.data.as_slice()).unwrap();
+    assert_eq!(counter_state.count, 1);
+}
+
+async fn create_token_account(
+    context: &mut solana_program_test::ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Pubkey {
+    let account = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let account_len = spl_token_2022::extension::ExtensionType::try_calculate_account_len::<
+        spl_token_2022::state::Account,
+    >(&[])
+    .unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        &[
+            system_instruction::create_account(
+                &context.payer.pubkey(),
+                &account.pubkey(),
+                rent.minimum_balance(account_len),
+                account_len as u64,
+                &spl_token_2022::id(),
+            ),
+            token_instruction::initialize_account3(&spl_token_2022::id(), &account.pubkey(), mint, owner).unwrap(),
+        ],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &account],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+    account.pubkey()
+}
+
+async fn create_and_fund_token_account(
+    context: &mut solana_program_test::ProgramTestContext,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    mint_authority: &Keypair,
+    amount: u64,
+) -> Pubkey {
+    let account = create_token_account(context, mint, owner).await;
+    let tx = Transaction::new_signed_with_payer(
+        &[token_instruction::mint_to(
+            &spl_token_2022::id(),
+            mint,
+            &account,
+            &mint_authority.pubkey(),
+            &[],
+            amount,
+        )
+        .unwrap()],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, mint_authority],
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+    account
+}